@@ -1,18 +1,13 @@
-use std::{
-	fs::File,
-	io::{Read, Seek, SeekFrom, Write},
-	path::Path
-};
+use std::{io::Write, path::Path};
 
 use kdam::BarExt;
 use ort::{
 	execution_providers::CUDAExecutionProvider,
 	memory::Allocator,
 	session::{Session, builder::SessionBuilder},
-	training::{CheckpointStrategy, Trainer, TrainerCallbacks, TrainerControl, TrainerState, TrainingArguments},
-	value::{Tensor, TensorRef}
+	training::{CheckpointStrategy, DataLoader, MmapTokenSource, SlidingWindowDataset, Trainer, TrainerCallbacks, TrainerControl, TrainerState, TrainingArguments},
+	value::TensorRef
 };
-use rand::RngCore;
 use tokenizers::Tokenizer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -72,44 +67,9 @@ fn main() -> ort::Result<()> {
 	)
 	.unwrap();
 
-	let mut dataset = File::open("train-clm-dataset.bin").unwrap();
-	let file_size = dataset.metadata().unwrap().len();
-	let num_tokens = (file_size / 2) as usize; // 16-bit tokens
-	let mut rng = rand::rng();
-	let mut input_buffer = vec![0u16; SEQUENCE_LENGTH * BATCH_SIZE];
-	let mut label_buffer = vec![0u16; SEQUENCE_LENGTH * BATCH_SIZE];
-	let dataloader = move |_: usize| {
-		for batch in 0..BATCH_SIZE {
-			let start_idx = rng.next_u64() % (num_tokens - SEQUENCE_LENGTH - 1) as u64;
-			dataset.seek(SeekFrom::Start(start_idx * 2)).unwrap();
-			dataset
-				.read_exact(unsafe {
-					std::slice::from_raw_parts_mut(
-						input_buffer[batch * SEQUENCE_LENGTH..(batch + 1) * SEQUENCE_LENGTH]
-							.as_mut_ptr()
-							.cast::<u8>(),
-						SEQUENCE_LENGTH * 2
-					)
-				})
-				.unwrap();
-			dataset.seek(SeekFrom::Start((start_idx + 1) * 2)).unwrap();
-			dataset
-				.read_exact(unsafe {
-					std::slice::from_raw_parts_mut(
-						label_buffer[batch * SEQUENCE_LENGTH..(batch + 1) * SEQUENCE_LENGTH]
-							.as_mut_ptr()
-							.cast::<u8>(),
-						SEQUENCE_LENGTH * 2
-					)
-				})
-				.unwrap();
-		}
-
-		let inputs = Tensor::from_array(([BATCH_SIZE, SEQUENCE_LENGTH], input_buffer.iter().map(|c| *c as i64).collect::<Vec<i64>>()))?;
-		let labels = Tensor::from_array(([BATCH_SIZE * SEQUENCE_LENGTH], label_buffer.iter().map(|c| *c as i64).collect::<Vec<i64>>()))?;
-
-		Ok((ort::inputs![inputs], ort::inputs![labels]))
-	};
+	let tokens = MmapTokenSource::open("train-clm-dataset.bin")?;
+	let dataset = SlidingWindowDataset::new(tokens, SEQUENCE_LENGTH);
+	let dataloader = DataLoader::new(dataset, BATCH_SIZE).with_shuffle(true);
 
 	trainer.train(
 		TrainingArguments::new(dataloader)
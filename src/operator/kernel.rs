@@ -1,10 +1,19 @@
 use alloc::{boxed::Box, ffi::CString, string::String, vec, vec::Vec};
 use core::{
+	any::Any,
 	ffi::{c_char, c_void},
 	mem::size_of,
+	panic::AssertUnwindSafe,
 	ptr::{self, NonNull},
 	slice
 };
+use std::{
+	panic::catch_unwind,
+	sync::{
+		Mutex,
+		atomic::{AtomicBool, Ordering}
+	}
+};
 
 use crate::{
 	AsPointer,
@@ -311,14 +320,25 @@ impl<'s, T: DowncastableTarget> GetKernelAttribute<'s> for ValueRef<'s, T> {
 pub struct ScratchBuffer<T> {
 	allocator: Allocator,
 	buffer: *mut T,
-	size: usize
+	size: usize,
+	/// Whether `buffer` was zero-filled when it was allocated. `false` for buffers created via
+	/// [`KernelContext::allocate_uninit`]; initializing their elements afterwards does not change it.
+	zeroed: bool
 }
 
 impl<T> ScratchBuffer<T> {
+	/// Returns the contents of this scratch buffer as a slice.
+	///
+	/// # Safety
+	/// If this buffer was created via [`KernelContext::allocate_uninit`], every element must have been initialized
+	/// before calling this - reading uninitialized memory through the returned `&[T]` is undefined behavior.
 	pub unsafe fn as_slice(&self) -> &[T] {
+		debug_assert!(self.zeroed, "reading a ScratchBuffer created via allocate_uninit before it was fully initialized");
 		unsafe { slice::from_raw_parts(self.buffer.cast_const(), self.size) }
 	}
 
+	/// Returns the contents of this scratch buffer as a mutable slice. See [`ScratchBuffer::as_slice`] for safety
+	/// requirements when this buffer was created via [`KernelContext::allocate_uninit`].
 	pub unsafe fn as_mut_slice(&mut self) -> &mut [T] {
 		unsafe { slice::from_raw_parts_mut(self.buffer, self.size) }
 	}
@@ -326,6 +346,8 @@ impl<T> ScratchBuffer<T> {
 
 impl<T> Drop for ScratchBuffer<T> {
 	fn drop(&mut self) {
+		// Always allocated via `AllocatorAlloc` (see `allocate_impl`), so freeing through `self.allocator` is always
+		// the buffer's actual owner - never the kernel-context scratch arena, which frees its own buffers itself.
 		unsafe {
 			self.allocator.free(self.buffer);
 		}
@@ -389,27 +411,60 @@ impl KernelContext {
 		Ok(())
 	}
 
-	// TODO: STATUS_ACCESS_VIOLATION inside `KernelContext_GetScratchBuffer`. gonna assume this one is just an internal ONNX
-	// Runtime bug.
-	//
-	// pub fn allocate<T>(&self, memory_info: &MemoryInfo, len: usize) -> Result<ScratchBuffer<T>> {
-	// 	let mut buffer = ptr::null_mut();
-	// 	let allocator = self.allocator(memory_info)?;
-	// 	ortsys![
-	// 		unsafe KernelContext_GetScratchBuffer(
-	// 			self.ptr.as_ptr(),
-	// 			memory_info.ptr.as_ptr(),
-	// 			len * core::mem::size_of::<T>(),
-	// 			&mut buffer
-	// 		)?;
-	// 		nonNull(buffer)
-	// 	];
-	// 	Ok(ScratchBuffer {
-	// 		allocator,
-	// 		buffer: buffer.cast::<T>(),
-	// 		size: len
-	// 	})
-	// }
+	/// Like [`KernelContext::par_for`], but `f` may fail. The first error raised by any shard (or any panic caught at
+	/// the FFI boundary, see [`trap_unwind`]) is recorded and returned once `KernelContext_ParallelFor` has finished
+	/// calling back into every shard; once an error has been recorded, remaining shards observe it and return
+	/// immediately instead of doing doomed work.
+	pub fn try_par_for<F>(&self, total: usize, max_num_batches: usize, f: F) -> Result<()>
+	where
+		F: Fn(usize) -> Result<()> + Sync + Send
+	{
+		let state = ParallelForState { f, failed: AtomicBool::new(false), error: Mutex::new(None) };
+		ortsys![unsafe KernelContext_ParallelFor(self.ptr.as_ptr(), try_parallel_for_cb::<F>, total, max_num_batches, &state as *const _ as *mut c_void)?];
+		match state.error.into_inner().expect("parallel-for error mutex was poisoned") {
+			Some(error) => Err(error),
+			None => Ok(())
+		}
+	}
+
+	/// Allocates a temporary, device-appropriate buffer of `len` elements of `T` for use within a single
+	/// [`Kernel::compute`] call. The buffer is zero-initialized and is automatically freed, on the correct device,
+	/// when the returned [`ScratchBuffer`] is dropped.
+	///
+	/// This goes through the [`Allocator`] returned by [`KernelContext::allocator`] rather than
+	/// `KernelContext_GetScratchBuffer`: on some ONNX Runtime builds that API crashes with `STATUS_ACCESS_VIOLATION`
+	/// instead of returning a failed `OrtStatus`, which is a hardware fault we have no way to catch or recover from
+	/// once it happens - so we never call it in the first place.
+	pub fn allocate<T>(&self, memory_info: &MemoryInfo, len: usize) -> Result<ScratchBuffer<T>> {
+		self.allocate_impl(memory_info, len, true)
+	}
+
+	/// Like [`KernelContext::allocate`], but the returned buffer's contents are left uninitialized instead of
+	/// zero-filled, avoiding the cost of zeroing a scratch buffer the kernel is about to overwrite in full anyway.
+	///
+	/// # Safety
+	/// Every element of the returned buffer must be initialized before it is read through
+	/// [`ScratchBuffer::as_slice`]/[`ScratchBuffer::as_mut_slice`].
+	pub unsafe fn allocate_uninit<T>(&self, memory_info: &MemoryInfo, len: usize) -> Result<ScratchBuffer<T>> {
+		self.allocate_impl(memory_info, len, false)
+	}
+
+	fn allocate_impl<T>(&self, memory_info: &MemoryInfo, len: usize, zeroed: bool) -> Result<ScratchBuffer<T>> {
+		let allocator = self.allocator(memory_info)?;
+		let size_bytes = len * size_of::<T>();
+
+		let mut buffer: *mut c_void = ptr::null_mut();
+		ortsys![unsafe AllocatorAlloc(allocator.ptr().cast_mut(), size_bytes, &mut buffer)?; nonNull(buffer)];
+		let buffer = buffer.cast::<T>();
+
+		assert_eq!(buffer.align_offset(core::mem::align_of::<T>()), 0, "scratch buffer returned by ONNX Runtime is misaligned for T");
+
+		if zeroed {
+			unsafe { ptr::write_bytes(buffer, 0, len) };
+		}
+
+		Ok(ScratchBuffer { allocator, buffer, size: len, zeroed })
+	}
 
 	/// Returns a pointer to the GPU compute stream (i.e. `cudaStream_t`) used by the execution provider, if this
 	/// kernel's operator was configured to use said execution provider (see
@@ -429,7 +484,60 @@ impl AsPointer for KernelContext {
 	}
 }
 
+/// Runs `f`, catching any panic it raises instead of letting it unwind across the FFI boundary into ONNX Runtime's
+/// C++, which is undefined behavior. A caught panic is converted into an [`Error`] carrying the panic message, so
+/// that callers of this function can report it back to the runtime as an `OrtStatus` instead of aborting the
+/// process. Used by [`parallel_for_cb`] and [`try_parallel_for_cb`] below.
+///
+/// Under `panic = "abort"` builds this still compiles (and still calls `f`), but since a panic there immediately
+/// aborts the process, `catch_unwind` never actually observes an `Err` - the wrapping is effectively a no-op.
+pub(crate) fn trap_unwind<R>(f: impl FnOnce() -> Result<R>) -> Result<R> {
+	match catch_unwind(AssertUnwindSafe(f)) {
+		Ok(result) => result,
+		Err(payload) => Err(panic_payload_to_error(payload))
+	}
+}
+
+fn panic_payload_to_error(payload: Box<dyn Any + Send>) -> Error {
+	let message = if let Some(message) = payload.downcast_ref::<&str>() {
+		String::from(*message)
+	} else if let Some(message) = payload.downcast_ref::<String>() {
+		message.clone()
+	} else {
+		String::from("custom operator kernel panicked with a non-string payload")
+	};
+	Error::new(format!("custom operator kernel panicked: {message}"))
+}
+
 extern "system" fn parallel_for_cb(user_data: *mut c_void, iterator: usize) {
 	let executor = unsafe { &*user_data.cast::<Box<dyn Fn(usize) + Sync + Send>>() };
-	executor(iterator)
+	// A panic here must never unwind into ONNX Runtime's C++ `KernelContext_ParallelFor` loop; there's no channel to
+	// report the failure back to the caller from this particular callback shape, so we just make sure it doesn't take
+	// the whole process down with it. See `try_par_for` for a variant that does propagate the error.
+	let _ = trap_unwind(|| {
+		executor(iterator);
+		Ok(())
+	});
+}
+
+struct ParallelForState<F> {
+	f: F,
+	/// Set once any shard has failed (via a returned `Err` or a caught panic), so that shards which haven't run yet
+	/// can skip their work cheaply instead of racing to completion after the batch is already doomed.
+	failed: AtomicBool,
+	/// The first error raised by any shard. Only ever written once, guarded by `failed`.
+	error: Mutex<Option<Error>>
+}
+
+extern "system" fn try_parallel_for_cb<F: Fn(usize) -> Result<()> + Sync + Send>(user_data: *mut c_void, iterator: usize) {
+	let state = unsafe { &*user_data.cast::<ParallelForState<F>>() };
+	if state.failed.load(Ordering::Relaxed) {
+		return;
+	}
+
+	if let Err(error) = trap_unwind(|| (state.f)(iterator)) {
+		if !state.failed.swap(true, Ordering::AcqRel) {
+			*state.error.lock().expect("parallel-for error mutex was poisoned") = Some(error);
+		}
+	}
 }
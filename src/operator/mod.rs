@@ -0,0 +1,171 @@
+//! Defining custom operators ([`Operator`]) for ONNX Runtime's operator extensibility API, and the `extern "system"`
+//! glue ONNX Runtime uses to call back into Rust across the `OrtCustomOp` vtable.
+
+use alloc::{boxed::Box, ffi::CString, vec::Vec};
+use core::{any::type_name, ffi::c_void, ptr};
+
+pub mod kernel;
+
+pub use self::kernel::{Kernel, KernelAttributes, KernelContext, ScratchBuffer};
+use self::kernel::trap_unwind;
+use crate::{
+	error::{Error, Result},
+	tensor::TensorElementType
+};
+
+/// A custom operator implementation, registered with a [`Session`](crate::session::Session) via an
+/// `OrtCustomOpDomain` to extend ONNX Runtime with ops it doesn't ship out of the box.
+///
+/// [`Operator::create_kernel`] is called by ONNX Runtime once per node using this op in the graph, producing the
+/// [`Kernel`] that actually does the work for that node.
+pub trait Operator {
+	type Kernel: Kernel;
+
+	/// This operator's name, as it appears in the ONNX graph.
+	fn name(&self) -> &str;
+
+	/// The execution provider this operator is implemented for (e.g. `"CPUExecutionProvider"`), or `None` to
+	/// register it for every execution provider in the session.
+	fn execution_provider_type(&self) -> Option<&str> {
+		None
+	}
+
+	/// The tensor element type of each input, in declaration order.
+	fn inputs(&self) -> Vec<TensorElementType>;
+	/// The tensor element type of each output, in declaration order.
+	fn outputs(&self) -> Vec<TensorElementType>;
+
+	/// Creates the [`Kernel`] that will execute this operator for a single node, using `info` to read that node's
+	/// attributes and input/output metadata.
+	fn create_kernel(&self, info: &KernelAttributes) -> Result<Self::Kernel>;
+}
+
+/// The allocation ONNX Runtime is actually given a pointer to when it's handed an `OrtCustomOp*`. ONNX Runtime's
+/// custom-op callbacks are passed a pointer to the `OrtCustomOp` struct itself (not to any user data), so - like the
+/// real ORT C++ custom op wrappers do - we put the vtable as the first, `repr(C)` field of our own struct, and
+/// recover `op` in each callback by reinterpreting that same pointer as a `*const CustomOpHandle<O>`.
+#[repr(C)]
+struct CustomOpHandle<O> {
+	base: ort_sys::OrtCustomOp,
+	op: O
+}
+
+/// Builds the boxed, pinned-address `OrtCustomOp` handle ONNX Runtime uses to call back into `op`. The returned
+/// pointer's `base` field is what gets registered with an `OrtCustomOpDomain`.
+///
+/// # Safety
+/// The returned pointer must stay alive and at a stable address for as long as the custom op domain it's registered
+/// under is alive - the caller is responsible for eventually reclaiming it (via [`Box::from_raw`]) after
+/// deregistering, and not before.
+pub(crate) unsafe fn bind_operator<O: Operator + 'static>(op: O) -> *mut CustomOpHandle<O> {
+	Box::into_raw(Box::new(CustomOpHandle {
+		base: ort_sys::OrtCustomOp {
+			version: ort_sys::ORT_API_VERSION,
+			CreateKernel: Some(create_kernel::<O>),
+			GetName: Some(get_name::<O>),
+			GetExecutionProviderType: Some(get_execution_provider_type::<O>),
+			GetInputTypeCount: Some(get_input_type_count::<O>),
+			GetInputType: Some(get_input_type::<O>),
+			GetOutputTypeCount: Some(get_output_type_count::<O>),
+			GetOutputType: Some(get_output_type::<O>),
+			KernelCompute: Some(compute_kernel::<O>),
+			KernelDestroy: Some(destroy_kernel::<O>),
+			..unsafe { core::mem::zeroed() }
+		},
+		op
+	}))
+}
+
+/// Converts a kernel/operator-callback [`Result`] into the `OrtStatusPtr` ONNX Runtime expects a failable
+/// `OrtCustomOp` entry point to return - `null` on success, a freshly-allocated status describing the error
+/// otherwise.
+fn status_from_result(result: Result<()>) -> *mut ort_sys::OrtStatus {
+	match result {
+		Ok(()) => ptr::null_mut(),
+		Err(e) => {
+			let message = CString::new(e.to_string()).unwrap_or_else(|_| CString::new("custom operator error").expect("static string has no NUL"));
+			unsafe { crate::api().CreateStatus.unwrap()(ort_sys::OrtErrorCode::ORT_FAIL, message.as_ptr()) }
+		}
+	}
+}
+
+extern "system" fn create_kernel<O: Operator>(
+	op: *const ort_sys::OrtCustomOp,
+	_api: *const ort_sys::OrtApi,
+	info: *const ort_sys::OrtKernelInfo,
+	kernel_out: *mut *mut c_void
+) -> *mut ort_sys::OrtStatus {
+	status_from_result(trap_unwind(|| {
+		let op = &unsafe { &*op.cast::<CustomOpHandle<O>>() }.op;
+		let info = KernelAttributes::from_ptr(ptr::NonNull::new(info.cast_mut()).expect("OrtKernelInfo must not be null"), false);
+		let kernel = op.create_kernel(&info)?;
+		unsafe { *kernel_out = Box::into_raw(Box::new(kernel)).cast::<c_void>() };
+		Ok(())
+	}))
+}
+
+extern "system" fn compute_kernel<O: Operator>(op_kernel: *mut c_void, context: *mut ort_sys::OrtKernelContext) -> *mut ort_sys::OrtStatus {
+	status_from_result(trap_unwind(|| {
+		let kernel = unsafe { &mut *op_kernel.cast::<O::Kernel>() };
+		let ctx = KernelContext::new(context);
+		// `ctx.input()`/`ctx.output()` hand back `ValueRef`/`ValueRefMut`s built via the `_nodrop` constructors, so
+		// they never take ownership of the underlying `OrtValue` - ONNX Runtime retains sole ownership of every
+		// input and output tensor throughout. That means a panic partway through `Kernel::compute`, caught here by
+		// `trap_unwind`, can never cause an output tensor to be freed twice: there is nothing for this stack frame
+		// to drop that ORT doesn't already own, regardless of how far `compute` got before unwinding.
+		kernel.compute(&ctx)
+	}))
+}
+
+extern "system" fn destroy_kernel<O: Operator>(op_kernel: *mut c_void) {
+	// Dropping a kernel shouldn't panic, but user `Drop` impls are as fallible as any other user code; catch it
+	// rather than letting it unwind across the FFI boundary into ONNX Runtime's kernel-teardown loop.
+	let _ = trap_unwind(|| {
+		drop(unsafe { Box::from_raw(op_kernel.cast::<O::Kernel>()) });
+		Ok(())
+	});
+}
+
+extern "system" fn get_name<O: Operator>(op: *const ort_sys::OrtCustomOp) -> *const core::ffi::c_char {
+	thread_local! {
+		static NAME: core::cell::RefCell<CString> = const { core::cell::RefCell::new(CString::new("").expect("static string has no NUL")) };
+	}
+	let op = &unsafe { &*op.cast::<CustomOpHandle<O>>() }.op;
+	let name = trap_unwind(|| CString::new(op.name()).map_err(Error::wrap)).unwrap_or_else(|_| CString::new(type_name::<O>()).unwrap_or_default());
+	NAME.with(|cell| {
+		*cell.borrow_mut() = name;
+		cell.borrow().as_ptr()
+	})
+}
+
+extern "system" fn get_execution_provider_type<O: Operator>(op: *const ort_sys::OrtCustomOp) -> *const core::ffi::c_char {
+	thread_local! {
+		static EP: core::cell::RefCell<Option<CString>> = const { core::cell::RefCell::new(None) };
+	}
+	let op = &unsafe { &*op.cast::<CustomOpHandle<O>>() }.op;
+	let ep = trap_unwind(|| op.execution_provider_type().map(|ep| CString::new(ep).map_err(Error::wrap)).transpose()).ok().flatten();
+	EP.with(|cell| {
+		*cell.borrow_mut() = ep;
+		cell.borrow().as_ref().map_or(ptr::null(), |ep| ep.as_ptr())
+	})
+}
+
+extern "system" fn get_input_type_count<O: Operator>(op: *const ort_sys::OrtCustomOp) -> usize {
+	let op = &unsafe { &*op.cast::<CustomOpHandle<O>>() }.op;
+	trap_unwind(|| Ok(op.inputs().len())).unwrap_or(0)
+}
+
+extern "system" fn get_input_type<O: Operator>(op: *const ort_sys::OrtCustomOp, index: usize) -> ort_sys::ONNXTensorElementDataType {
+	let op = &unsafe { &*op.cast::<CustomOpHandle<O>>() }.op;
+	trap_unwind(|| Ok(op.inputs().get(index).copied().unwrap_or(TensorElementType::Undefined).into())).unwrap_or(ort_sys::ONNXTensorElementDataType::ONNX_TENSOR_ELEMENT_DATA_TYPE_UNDEFINED)
+}
+
+extern "system" fn get_output_type_count<O: Operator>(op: *const ort_sys::OrtCustomOp) -> usize {
+	let op = &unsafe { &*op.cast::<CustomOpHandle<O>>() }.op;
+	trap_unwind(|| Ok(op.outputs().len())).unwrap_or(0)
+}
+
+extern "system" fn get_output_type<O: Operator>(op: *const ort_sys::OrtCustomOp, index: usize) -> ort_sys::ONNXTensorElementDataType {
+	let op = &unsafe { &*op.cast::<CustomOpHandle<O>>() }.op;
+	trap_unwind(|| Ok(op.outputs().get(index).copied().unwrap_or(TensorElementType::Undefined).into())).unwrap_or(ort_sys::ONNXTensorElementDataType::ONNX_TENSOR_ELEMENT_DATA_TYPE_UNDEFINED)
+}
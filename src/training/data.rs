@@ -0,0 +1,262 @@
+//! Batching, shuffling, and sliding-window token sampling for [`Trainer::train`](super::Trainer::train), so that
+//! training scripts don't need to hand-roll batching over raw files.
+
+use std::{fs::File, mem::size_of, path::Path};
+
+use rand::{RngCore, SeedableRng, rngs::StdRng};
+
+use crate::{
+	error::{Error, Result},
+	value::{Tensor, Value}
+};
+
+/// A dataset of individually-addressable, fixed-shape samples. [`DataLoader`] batches these up for training.
+pub trait Dataset {
+	type Sample;
+
+	/// The total number of samples in this dataset.
+	fn len(&self) -> usize;
+
+	/// Fetches the sample at `index`, where `index < self.len()`.
+	fn get(&self, index: usize) -> Result<Self::Sample>;
+
+	fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+/// A flat buffer of `u16` tokens backed by a memory-mapped file, so the whole corpus doesn't need to be read into
+/// RAM up front - pages are faulted in by the OS as [`SlidingWindowDataset`] reads from them.
+pub struct MmapTokenSource {
+	mmap: memmap2::Mmap,
+	len: usize
+}
+
+impl MmapTokenSource {
+	/// Memory-maps `path` as a flat buffer of little-endian `u16` tokens.
+	pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+		let file = File::open(path.as_ref()).map_err(Error::wrap)?;
+		let mmap = unsafe { memmap2::Mmap::map(&file) }.map_err(Error::wrap)?;
+		let len = mmap.len() / size_of::<u16>();
+		Ok(Self { mmap, len })
+	}
+
+	/// The number of tokens in the corpus.
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	fn read_into(&self, start: usize, buf: &mut [u16]) {
+		let byte_start = start * size_of::<u16>();
+		let bytes = &self.mmap[byte_start..byte_start + buf.len() * size_of::<u16>()];
+		for (raw, token) in bytes.chunks_exact(size_of::<u16>()).zip(buf.iter_mut()) {
+			*token = u16::from_le_bytes([raw[0], raw[1]]);
+		}
+	}
+}
+
+/// A [`Dataset`] that samples fixed-length, overlapping `(input, label)` windows out of a flat token buffer, where
+/// `label` is `input` shifted forward by one token - the standard setup for causal language model training.
+pub struct SlidingWindowDataset {
+	tokens: MmapTokenSource,
+	sequence_length: usize
+}
+
+impl SlidingWindowDataset {
+	pub fn new(tokens: MmapTokenSource, sequence_length: usize) -> Self {
+		Self { tokens, sequence_length }
+	}
+}
+
+impl Dataset for SlidingWindowDataset {
+	type Sample = (Vec<i64>, Vec<i64>);
+
+	fn len(&self) -> usize {
+		self.tokens.len().saturating_sub(self.sequence_length)
+	}
+
+	fn get(&self, index: usize) -> Result<Self::Sample> {
+		if index >= self.len() {
+			return Err(Error::new("sliding-window sample index out of bounds"));
+		}
+
+		let mut window = vec![0u16; self.sequence_length + 1];
+		self.tokens.read_into(index, &mut window);
+
+		let input = window[..self.sequence_length].iter().map(|&token| token as i64).collect();
+		let label = window[1..].iter().map(|&token| token as i64).collect();
+		Ok((input, label))
+	}
+}
+
+/// Batches a [`Dataset`] of `(input, label)` token sequences for [`TrainingArguments::new`](super::TrainingArguments::new),
+/// handling shuffling, batch stacking, and optionally dropping an incomplete trailing batch.
+pub struct DataLoader<D: Dataset<Sample = (Vec<i64>, Vec<i64>)>> {
+	dataset: D,
+	batch_size: usize,
+	shuffle: bool,
+	drop_last: bool,
+	seed: u64,
+	order: Vec<usize>,
+	shuffled_epoch: Option<u64>
+}
+
+impl<D: Dataset<Sample = (Vec<i64>, Vec<i64>)>> DataLoader<D> {
+	pub fn new(dataset: D, batch_size: usize) -> Self {
+		let order = (0..dataset.len()).collect();
+		Self { dataset, batch_size, shuffle: true, drop_last: false, seed: 0, order, shuffled_epoch: None }
+	}
+
+	pub fn with_shuffle(mut self, shuffle: bool) -> Self {
+		self.shuffle = shuffle;
+		self
+	}
+
+	pub fn with_drop_last(mut self, drop_last: bool) -> Self {
+		self.drop_last = drop_last;
+		self
+	}
+
+	/// Sets the seed used to shuffle sample order each epoch. Two `DataLoader`s with the same dataset, seed, and
+	/// epoch will always yield batches in the same order.
+	pub fn with_seed(mut self, seed: u64) -> Self {
+		self.seed = seed;
+		self
+	}
+
+	/// The number of batches in one epoch over the dataset.
+	pub fn num_batches(&self) -> usize {
+		if self.dataset.is_empty() || self.batch_size == 0 {
+			return 0;
+		}
+		if self.drop_last { self.dataset.len() / self.batch_size } else { self.dataset.len().div_ceil(self.batch_size) }
+	}
+
+	fn ensure_epoch_order(&mut self, epoch: u64) {
+		if !self.shuffle {
+			return;
+		}
+		if self.shuffled_epoch == Some(epoch) {
+			return;
+		}
+
+		// Always reshuffle from the fixed `0..len` base order, never the permutation a previous epoch left behind,
+		// so that `(seed, epoch)` alone determines the order regardless of which epochs were visited beforehand.
+		self.order.clear();
+		self.order.extend(0..self.dataset.len());
+
+		let mut rng = StdRng::seed_from_u64(self.seed ^ epoch);
+		for i in (1..self.order.len()).rev() {
+			let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+			self.order.swap(i, j);
+		}
+		self.shuffled_epoch = Some(epoch);
+	}
+
+	/// Computes the dataset indices making up the batch for global training `step`, shuffling the epoch's order
+	/// first if needed. Pulled out of [`DataLoader::next_batch`] so the index-selection logic can be unit tested
+	/// without needing an ORT runtime to stack tensors.
+	fn batch_indices(&mut self, step: usize) -> Result<&[usize]> {
+		let num_batches = self.num_batches();
+		if num_batches == 0 {
+			return Err(Error::new("DataLoader has no batches to yield - is the dataset empty?"));
+		}
+
+		let epoch = (step / num_batches) as u64;
+		let batch = step % num_batches;
+		self.ensure_epoch_order(epoch);
+
+		let start = batch * self.batch_size;
+		let end = (start + self.batch_size).min(self.order.len());
+		Ok(&self.order[start..end])
+	}
+
+	/// Produces the batch of inputs/labels for global training `step`, stacked into `[batch, sequence_length]`
+	/// tensors, wrapping around to a newly-shuffled epoch once every batch has been visited.
+	pub(crate) fn next_batch(&mut self, step: usize) -> Result<(Vec<Value>, Vec<Value>)> {
+		let indices = self.batch_indices(step)?;
+
+		let mut inputs = Vec::with_capacity(indices.len());
+		let mut labels = Vec::with_capacity(indices.len());
+		for &index in indices {
+			let (input, label) = self.dataset.get(index)?;
+			inputs.push(input);
+			labels.push(label);
+		}
+
+		Ok((vec![stack(inputs)?], vec![stack(labels)?]))
+	}
+}
+
+fn stack(rows: Vec<Vec<i64>>) -> Result<Value> {
+	let batch_size = rows.len();
+	let sequence_length = rows.first().map_or(0, Vec::len);
+	let flat: Vec<i64> = rows.into_iter().flatten().collect();
+	Ok(Tensor::from_array(([batch_size, sequence_length], flat))?.into())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A [`Dataset`] whose samples are just their own index, so tests can assert on exactly which indices a batch
+	/// contains without needing real token data.
+	struct IndexDataset(usize);
+
+	impl Dataset for IndexDataset {
+		type Sample = (Vec<i64>, Vec<i64>);
+
+		fn len(&self) -> usize {
+			self.0
+		}
+
+		fn get(&self, index: usize) -> Result<Self::Sample> {
+			Ok((vec![index as i64], vec![index as i64]))
+		}
+	}
+
+	fn collect_epoch(loader: &mut DataLoader<IndexDataset>, epoch: u64) -> Vec<usize> {
+		let num_batches = loader.num_batches();
+		let mut indices = Vec::new();
+		for batch in 0..num_batches {
+			let step = epoch as usize * num_batches + batch;
+			indices.extend(loader.batch_indices(step).unwrap().iter().copied());
+		}
+		indices
+	}
+
+	#[test]
+	fn epoch_order_is_independent_of_previously_visited_epochs() {
+		let mut direct = DataLoader::new(IndexDataset(20), 4).with_seed(42);
+		let direct_epoch_5 = collect_epoch(&mut direct, 5);
+
+		let mut stepped = DataLoader::new(IndexDataset(20), 4).with_seed(42);
+		for epoch in 0..5 {
+			collect_epoch(&mut stepped, epoch);
+		}
+		let stepped_epoch_5 = collect_epoch(&mut stepped, 5);
+
+		assert_eq!(direct_epoch_5, stepped_epoch_5, "same (seed, epoch) must yield the same order regardless of path taken to reach it");
+	}
+
+	#[test]
+	fn shuffled_epoch_visits_every_index_exactly_once() {
+		let mut loader = DataLoader::new(IndexDataset(17), 4).with_seed(7);
+		let mut indices = collect_epoch(&mut loader, 3);
+		indices.sort_unstable();
+		assert_eq!(indices, (0..17).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn drop_last_excludes_incomplete_trailing_batch() {
+		let loader = DataLoader::new(IndexDataset(10), 3).with_drop_last(true);
+		assert_eq!(loader.num_batches(), 3);
+
+		let loader = DataLoader::new(IndexDataset(10), 3).with_drop_last(false);
+		assert_eq!(loader.num_batches(), 4);
+	}
+}
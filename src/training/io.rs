@@ -0,0 +1,180 @@
+//! Byte-sink abstraction for reading and writing training checkpoints and exported models, so the training
+//! subsystem isn't hard-wired to the local filesystem.
+
+use std::{
+	ffi::CString,
+	fs::File,
+	io::{Read, Seek, Write},
+	path::{Path, PathBuf},
+	ptr::NonNull,
+	sync::{Arc, Mutex}
+};
+
+use tempfile::NamedTempFile;
+
+use crate::{
+	error::{Error, Result},
+	ortsys
+};
+
+/// A destination for a single training checkpoint, used by [`CheckpointStrategy::ToSink`](super::CheckpointStrategy::ToSink).
+///
+/// Implement this to stream checkpoints somewhere other than a fixed path on disk - object storage, a database blob
+/// column, or an in-memory buffer.
+pub trait CheckpointSink: Send {
+	/// Returns a fresh writer for the checkpoint taken at `step`.
+	fn create(&mut self, step: usize) -> Result<Box<dyn Write + Send>>;
+}
+
+/// The default [`CheckpointSink`] used by [`CheckpointStrategy::Steps`](super::CheckpointStrategy::Steps): writes
+/// each checkpoint to `{dir}/checkpoint-{step}` on the local filesystem.
+pub struct PathSink {
+	dir: PathBuf
+}
+
+impl PathSink {
+	pub fn new(dir: impl Into<PathBuf>) -> Self {
+		Self { dir: dir.into() }
+	}
+}
+
+impl CheckpointSink for PathSink {
+	fn create(&mut self, step: usize) -> Result<Box<dyn Write + Send>> {
+		std::fs::create_dir_all(&self.dir).map_err(Error::wrap)?;
+		let file = File::create(self.dir.join(format!("checkpoint-{step}"))).map_err(Error::wrap)?;
+		Ok(Box::new(file))
+	}
+}
+
+pub(crate) fn path_to_cstring(path: &Path) -> Result<CString> {
+	CString::new(path.to_string_lossy().into_owned()).map_err(Error::wrap)
+}
+
+fn copy_temp_file_to(file: &mut File, writer: &mut dyn Write) -> Result<()> {
+	file.rewind().map_err(Error::wrap)?;
+	std::io::copy(file, writer).map_err(Error::wrap)?;
+	Ok(())
+}
+
+/// Writes `checkpoint_ptr`'s state to `writer`. `SaveCheckpoint` is a path-based ONNX Runtime API, so this bounces
+/// the checkpoint through a securely-created throwaway temp file (random name, exclusive creation, restrictive
+/// permissions, removed on drop) and streams that file's bytes into `writer`.
+pub(crate) fn save_checkpoint(checkpoint_ptr: NonNull<ort_sys::OrtCheckpointState>, writer: &mut dyn Write) -> Result<()> {
+	let mut tmp = NamedTempFile::with_prefix("ort-checkpoint-").map_err(Error::wrap)?;
+	let c_path = path_to_cstring(tmp.path())?;
+	ortsys![unsafe SaveCheckpoint(checkpoint_ptr.as_ptr(), c_path.as_ptr(), false)?];
+	copy_temp_file_to(tmp.as_file_mut(), writer)
+}
+
+/// Reads a checkpoint back from `reader`, bouncing it through a securely-created throwaway temp file for the same
+/// reason as [`save_checkpoint`].
+pub(crate) fn load_checkpoint(reader: &mut (impl Read + Seek)) -> Result<NonNull<ort_sys::OrtCheckpointState>> {
+	let mut tmp = NamedTempFile::with_prefix("ort-checkpoint-load-").map_err(Error::wrap)?;
+	reader.rewind().map_err(Error::wrap)?;
+	std::io::copy(reader, tmp.as_file_mut()).map_err(Error::wrap)?;
+	tmp.as_file_mut().sync_all().map_err(Error::wrap)?;
+
+	let c_path = path_to_cstring(tmp.path())?;
+	let mut checkpoint_ptr: *mut ort_sys::OrtCheckpointState = std::ptr::null_mut();
+	ortsys![unsafe LoadCheckpoint(c_path.as_ptr(), &mut checkpoint_ptr)?; nonNull(checkpoint_ptr)];
+	Ok(unsafe { NonNull::new_unchecked(checkpoint_ptr) })
+}
+
+/// Exports `session_ptr`'s model graph (with `output_names` as graph outputs) for inference, writing the resulting
+/// ONNX model into `writer`. `TrainingSessionExportModelForInferencing` is path-based, so this bounces the exported
+/// model through a securely-created throwaway temp file, same as [`save_checkpoint`].
+pub(crate) fn export_for_inference<W: Write>(
+	session_ptr: NonNull<ort_sys::OrtTrainingSession>,
+	writer: &mut W,
+	output_names: impl IntoIterator<Item = impl AsRef<str>>
+) -> Result<()> {
+	let output_names = output_names
+		.into_iter()
+		.map(|name| CString::new(name.as_ref()).map_err(Error::wrap))
+		.collect::<Result<Vec<_>>>()?;
+	let output_name_ptrs: Vec<*const ort_sys::c_char> = output_names.iter().map(|name| name.as_ptr()).collect();
+
+	let mut tmp = NamedTempFile::with_prefix("ort-export-").map_err(Error::wrap)?;
+	let c_path = path_to_cstring(tmp.path())?;
+	ortsys![unsafe TrainingSessionExportModelForInferencing(session_ptr.as_ptr(), c_path.as_ptr(), output_name_ptrs.len(), output_name_ptrs.as_ptr())?];
+
+	copy_temp_file_to(tmp.as_file_mut(), writer)
+}
+
+/// A [`CheckpointSink`] that keeps every checkpoint in memory instead of writing to disk, e.g. for tests or for
+/// forwarding checkpoints to another part of the process without touching the filesystem at all.
+#[derive(Clone, Default)]
+pub struct MemorySink {
+	buffers: Arc<Mutex<Vec<Vec<u8>>>>
+}
+
+impl MemorySink {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Returns every checkpoint written so far, in the order their writers were dropped. A checkpoint whose writer
+	/// is still alive is not included.
+	pub fn checkpoints(&self) -> Vec<Vec<u8>> {
+		self.buffers.lock().expect("memory sink mutex was poisoned").clone()
+	}
+}
+
+impl CheckpointSink for MemorySink {
+	fn create(&mut self, _step: usize) -> Result<Box<dyn Write + Send>> {
+		struct SlotWriter {
+			buffers: Arc<Mutex<Vec<Vec<u8>>>>,
+			data: Vec<u8>
+		}
+
+		impl Write for SlotWriter {
+			fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+				self.data.write(buf)
+			}
+
+			fn flush(&mut self) -> std::io::Result<()> {
+				Ok(())
+			}
+		}
+
+		impl Drop for SlotWriter {
+			fn drop(&mut self) {
+				self.buffers.lock().expect("memory sink mutex was poisoned").push(std::mem::take(&mut self.data));
+			}
+		}
+
+		Ok(Box::new(SlotWriter { buffers: self.buffers.clone(), data: Vec::new() }))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn memory_sink_accumulates_in_creation_order() {
+		let mut sink = MemorySink::new();
+
+		let mut first = sink.create(0).unwrap();
+		first.write_all(b"first").unwrap();
+		drop(first);
+
+		let mut second = sink.create(1).unwrap();
+		second.write_all(b"second").unwrap();
+		drop(second);
+
+		assert_eq!(sink.checkpoints(), vec![b"first".to_vec(), b"second".to_vec()]);
+	}
+
+	#[test]
+	fn memory_sink_writer_is_empty_until_dropped() {
+		let mut sink = MemorySink::new();
+		let mut writer = sink.create(0).unwrap();
+		writer.write_all(b"pending").unwrap();
+
+		assert!(sink.checkpoints().is_empty(), "checkpoint should only be visible once its writer is dropped");
+
+		drop(writer);
+		assert_eq!(sink.checkpoints(), vec![b"pending".to_vec()]);
+	}
+}